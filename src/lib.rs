@@ -0,0 +1,39 @@
+//! Parsers for files under `/proc`.
+mod util;
+
+pub mod stat;
+
+pub use stat::Stat;
+
+use std::fmt;
+use std::io;
+
+/// An error encountered while parsing a `/proc` file.
+#[derive(Debug)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<&str> for Error {
+    fn from(msg: &str) -> Self {
+        Error(msg.to_owned())
+    }
+}
+
+impl From<String> for Error {
+    fn from(msg: String) -> Self {
+        Error(msg)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
@@ -1,6 +1,21 @@
 //! Bindings to `/proc/stat`.
 use crate::{util, Error};
-use std::{fs::File, io};
+use std::{fs::File, io, path::Path};
+
+/// Parses a type from an already-open reader, or from a file on disk.
+///
+/// Implementing this instead of an inherent `from_reader` lets callers point parsing at a file
+/// other than the hardcoded system path, e.g. a bind-mounted `/proc` from another container or
+/// namespace, or a captured fixture used in tests.
+pub trait FromRead: Sized {
+    /// Parses `Self` from an already-open reader.
+    fn from_read(reader: impl io::Read) -> io::Result<Self>;
+
+    /// Parses `Self` from the file at `path`.
+    fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_read(File::open(path)?)
+    }
+}
 
 macro_rules! parse_single {
     ($name:expr) => {
@@ -24,11 +39,14 @@ macro_rules! parse_single {
 
 /// The stats from `/proc/stat`.
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 pub struct Stat {
     /// Total stats, sum of all cpus.
     pub cpu_totals: StatCpu,
     /// For each cpu, the number of *units* spent in different contexts.
     pub cpus: Vec<StatCpu>,
+    /// Counts of interrupts serviced, broken down by interrupt source.
+    pub interrupts: Interrupts,
     /// Number of context switches since the system booted.
     pub context_switches: u64,
     /// Timestamp (in seconds since epoch) that system booted.
@@ -39,7 +57,8 @@ pub struct Stat {
     pub procs_running: u64,
     /// The total number of processes waiting to run on the cpu.
     pub procs_blocked: u64,
-    // todo `softirq`
+    /// Counts of softirqs serviced, broken down by softirq source.
+    pub softirq: SoftIrq,
 }
 
 impl Stat {
@@ -47,35 +66,61 @@ impl Stat {
 
     /// Parse the contents of `/proc/stat`.
     pub fn from_system() -> io::Result<Self> {
-        Stat::from_reader(File::open(Self::PATH)?)
+        Self::from_file(Self::PATH)
+    }
+
+    /// Parse the contents of `stat` under the given `/proc` root.
+    ///
+    /// Use this instead of [`Stat::from_system`] when `/proc` is bind-mounted at a non-standard
+    /// path, e.g. for inspecting another container's or namespace's process table.
+    pub fn from_root(proc_root: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_file(proc_root.as_ref().join("stat"))
+    }
+
+    /// Computes per-cpu and aggregate usage fractions between two samples.
+    ///
+    /// Returns an error if `earlier` is from before a reboot (detected via a change in
+    /// `boot_time`), since jiffy counters are not comparable across boots.
+    pub fn usage_since(&self, earlier: &Stat) -> Result<StatUsage, Error> {
+        if self.boot_time != earlier.boot_time {
+            return Err(Error::from("samples are from different boots"));
+        }
+        let total = self.cpu_totals.usage_since(&earlier.cpu_totals)?;
+        let cpus = self
+            .cpus
+            .iter()
+            .zip(earlier.cpus.iter())
+            .map(|(a, b)| a.usage_since(b))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(StatUsage { total, cpus })
     }
+}
 
-    fn from_reader(reader: impl io::Read) -> io::Result<Self> {
+impl FromRead for Stat {
+    fn from_read(reader: impl io::Read) -> io::Result<Self> {
         let mut reader = util::LineParser::new(reader);
         let cpu_totals = reader.parse_line(StatCpu::from_str)?;
         let mut cpus = Vec::new();
-        loop {
-            if let Ok(cpu_info) = reader.parse_line(StatCpu::from_str) {
-                cpus.push(cpu_info);
-            } else {
-                break;
-            }
+        while let Ok(cpu_info) = reader.parse_line(StatCpu::from_str) {
+            cpus.push(cpu_info);
         }
-        reader.parse_line(util::parse_dummy)?;
+        let interrupts = reader.parse_line(Interrupts::from_str)?;
         let context_switches = reader.parse_line(parse_single!("ctxt"))?;
         let boot_time = reader.parse_line(parse_single!("btime"))?;
         let processes = reader.parse_line(parse_single!("processes"))?;
         let procs_running = reader.parse_line(parse_single!("procs_running"))?;
         let procs_blocked = reader.parse_line(parse_single!("procs_blocked"))?;
-        // todo softirq
+        let softirq = reader.parse_line(SoftIrq::from_str)?;
         Ok(Stat {
             cpu_totals,
             cpus,
+            interrupts,
             context_switches,
             boot_time,
             processes,
             procs_running,
             procs_blocked,
+            softirq,
         })
     }
 }
@@ -85,6 +130,7 @@ impl Stat {
 /// *units* could be anything, for example cpu cycles, or hundredths of a second. The numbers only
 /// really make sense as a proportion of the total.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 pub struct StatCpu {
     pub user: u64,
     pub nice: u64,
@@ -147,26 +193,178 @@ impl StatCpu {
     }
 
     /// Convenience function to add up all cpu values.
+    ///
+    /// Saturates at `u64::MAX` rather than panicking if the fields ever overflow when summed; use
+    /// [`StatCpu::checked_total`] if you need to detect that instead.
     pub fn total(&self) -> u64 {
+        self.checked_total().unwrap_or(u64::MAX)
+    }
+
+    /// Like [`StatCpu::total`], but returns `None` instead of saturating on overflow.
+    pub fn checked_total(&self) -> Option<u64> {
         self.user
-            .checked_add(self.nice)
-            .unwrap()
-            .checked_add(self.system)
-            .unwrap()
-            .checked_add(self.idle)
-            .unwrap()
-            .checked_add(self.iowait)
-            .unwrap()
-            .checked_add(self.irq)
-            .unwrap()
-            .checked_add(self.softirq)
-            .unwrap()
-            .checked_add(self.steal.unwrap_or(0))
-            .unwrap()
-            .checked_add(self.guest.unwrap_or(0))
-            .unwrap()
+            .checked_add(self.nice)?
+            .checked_add(self.system)?
+            .checked_add(self.idle)?
+            .checked_add(self.iowait)?
+            .checked_add(self.irq)?
+            .checked_add(self.softirq)?
+            .checked_add(self.steal.unwrap_or(0))?
+            .checked_add(self.guest.unwrap_or(0))?
             .checked_add(self.guest_nice.unwrap_or(0))
-            .unwrap()
+    }
+
+    /// Time spent idle, including waiting on I/O: `idle + iowait`.
+    pub fn idle_time(&self) -> u64 {
+        self.idle.saturating_add(self.iowait)
+    }
+
+    /// Time spent busy, i.e. not idle: `total() - idle_time()`.
+    pub fn busy_time(&self) -> u64 {
+        self.total().saturating_sub(self.idle_time())
+    }
+
+    /// Computes the fraction of time spent in each context between two samples of the same cpu.
+    ///
+    /// `earlier` should be an older sample of the same cpu. Returns an error if the total jiffies
+    /// did not increase, which happens if `earlier` is not actually older than `self` or the
+    /// counters wrapped.
+    pub fn usage_since(&self, earlier: &StatCpu) -> Result<CpuUsage, Error> {
+        let total_delta = self
+            .total()
+            .checked_sub(earlier.total())
+            .ok_or_else(|| Error::from("total cpu time went backwards"))?;
+        if total_delta == 0 {
+            return Err(Error::from("no elapsed cpu time between samples"));
+        }
+        let total_delta = total_delta as f64;
+        let frac = |a: u64, b: u64| a.saturating_sub(b) as f64 / total_delta;
+
+        let idle = frac(self.idle, earlier.idle);
+        let iowait = frac(self.iowait, earlier.iowait);
+        Ok(CpuUsage {
+            user: frac(self.user, earlier.user),
+            nice: frac(self.nice, earlier.nice),
+            system: frac(self.system, earlier.system),
+            idle,
+            iowait,
+            irq: frac(self.irq, earlier.irq),
+            softirq: frac(self.softirq, earlier.softirq),
+            steal: frac(self.steal.unwrap_or(0), earlier.steal.unwrap_or(0)),
+            guest: frac(self.guest.unwrap_or(0), earlier.guest.unwrap_or(0)),
+            guest_nice: frac(self.guest_nice.unwrap_or(0), earlier.guest_nice.unwrap_or(0)),
+            busy: 1.0 - (idle + iowait),
+        })
+    }
+}
+
+/// Fraction of time (0.0–1.0) spent in each cpu context, computed by differencing two
+/// [`StatCpu`] samples.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CpuUsage {
+    pub user: f64,
+    pub nice: f64,
+    pub system: f64,
+    pub idle: f64,
+    pub iowait: f64,
+    pub irq: f64,
+    pub softirq: f64,
+    pub steal: f64,
+    pub guest: f64,
+    pub guest_nice: f64,
+    /// Overall fraction of time spent busy, i.e. `1.0 - (idle + iowait)`.
+    pub busy: f64,
+}
+
+/// Per-cpu and aggregate usage fractions between two [`Stat`] snapshots.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatUsage {
+    /// Aggregate usage across all cpus, from `cpu_totals`.
+    pub total: CpuUsage,
+    /// Usage for each cpu, zipped by index between the two samples.
+    pub cpus: Vec<CpuUsage>,
+}
+
+/// Parses a line of the form `<name> <total> <count> <count> ...`, returning the total and the
+/// remaining counts in file order.
+fn parse_counts(input: &str, name: &str) -> Result<(u64, Vec<u64>), Error> {
+    let (input, actual_name) = util::parse_token(input).ok_or(Error::from("cannot read name"))?;
+    if actual_name != name {
+        return Err(Error::from(format!(
+            "incorrect name, expected: {}, actual: {}",
+            name, actual_name
+        )));
+    }
+    let (mut input, total) = util::parse_u64(input).ok_or(Error::from("cannot read total"))?;
+    let mut counts = Vec::new();
+    while let Some((rest, count)) = util::parse_u64(input) {
+        counts.push(count);
+        input = rest;
+    }
+    let input = util::consume_space(input);
+    if !input.is_empty() {
+        return Err(Error::from("trailing content"));
+    }
+    Ok((total, counts))
+}
+
+/// Counts of interrupts serviced since boot, parsed from the `intr` line of `/proc/stat`.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Interrupts {
+    /// Total of all interrupts serviced, including unnumbered architecture-specific ones.
+    pub total: u64,
+    /// Per-source interrupt counts, in the order the kernel reports them.
+    pub per_source: Vec<u64>,
+}
+
+impl Interrupts {
+    fn from_str(input: &str) -> Result<Interrupts, Error> {
+        let (total, per_source) = parse_counts(input, "intr")?;
+        Ok(Interrupts { total, per_source })
+    }
+}
+
+/// Counts of softirqs serviced since boot, parsed from the `softirq` line of `/proc/stat`.
+///
+/// The named fields mirror the kernel's `enum` of softirq vectors, in the order it reports them.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct SoftIrq {
+    /// Total of all softirqs serviced, summed across all sources.
+    pub total: u64,
+    /// Per-source softirq counts, in the order the kernel reports them.
+    pub per_source: Vec<u64>,
+    pub hi: u64,
+    pub timer: u64,
+    pub net_tx: u64,
+    pub net_rx: u64,
+    pub block: u64,
+    pub irq_poll: u64,
+    pub tasklet: u64,
+    pub sched: u64,
+    pub hrtimer: u64,
+    pub rcu: u64,
+}
+
+impl SoftIrq {
+    fn from_str(input: &str) -> Result<SoftIrq, Error> {
+        let (total, per_source) = parse_counts(input, "softirq")?;
+        let get = |i: usize| per_source.get(i).copied().unwrap_or(0);
+        Ok(SoftIrq {
+            total,
+            hi: get(0),
+            timer: get(1),
+            net_tx: get(2),
+            net_rx: get(3),
+            block: get(4),
+            irq_poll: get(5),
+            tasklet: get(6),
+            sched: get(7),
+            hrtimer: get(8),
+            rcu: get(9),
+            per_source,
+        })
     }
 }
 
@@ -186,5 +384,135 @@ procs_running 1
 procs_blocked 0
 softirq 4257581 64 299604 69 2986 36581 0 3497229 283111 0 137937
 ";
-    let _stat = Stat::from_reader(io::Cursor::new(raw)).unwrap();
+    let stat = Stat::from_read(io::Cursor::new(raw)).unwrap();
+    assert_eq!(stat.interrupts.total, 1015182);
+    assert_eq!(stat.softirq.total, 4257581);
+    assert_eq!(stat.softirq.net_tx, 69);
+    assert_eq!(stat.softirq.rcu, 137937);
+}
+
+#[test]
+fn test_cpu_usage_since() {
+    let earlier = StatCpu {
+        user: 100,
+        nice: 0,
+        system: 0,
+        idle: 900,
+        iowait: 0,
+        irq: 0,
+        softirq: 0,
+        steal: None,
+        guest: None,
+        guest_nice: None,
+    };
+    let later = StatCpu {
+        user: 200,
+        idle: 1000,
+        ..earlier
+    };
+    let usage = later.usage_since(&earlier).unwrap();
+    assert_eq!(usage.user, 0.5);
+    assert_eq!(usage.idle, 0.5);
+    assert_eq!(usage.busy, 0.5);
+
+    assert!(earlier.usage_since(&later).is_err());
+    assert!(earlier.usage_since(&earlier).is_err());
+}
+
+#[cfg(test)]
+fn make_stat(boot_time: u64, user: u64, idle: u64) -> Stat {
+    let cpu = StatCpu {
+        user,
+        nice: 0,
+        system: 0,
+        idle,
+        iowait: 0,
+        irq: 0,
+        softirq: 0,
+        steal: None,
+        guest: None,
+        guest_nice: None,
+    };
+    Stat {
+        cpu_totals: cpu,
+        cpus: vec![cpu],
+        interrupts: Interrupts {
+            total: 0,
+            per_source: vec![],
+        },
+        context_switches: 0,
+        boot_time,
+        processes: 0,
+        procs_running: 0,
+        procs_blocked: 0,
+        softirq: SoftIrq {
+            total: 0,
+            per_source: vec![],
+            hi: 0,
+            timer: 0,
+            net_tx: 0,
+            net_rx: 0,
+            block: 0,
+            irq_poll: 0,
+            tasklet: 0,
+            sched: 0,
+            hrtimer: 0,
+            rcu: 0,
+        },
+    }
+}
+
+#[test]
+fn test_stat_usage_since() {
+    let earlier = make_stat(1_000, 100, 900);
+    let later = make_stat(1_000, 200, 1_000);
+    let usage = later.usage_since(&earlier).unwrap();
+    assert_eq!(usage.total.user, 0.5);
+    assert_eq!(usage.cpus.len(), 1);
+    assert_eq!(usage.cpus[0].user, 0.5);
+
+    let rebooted = make_stat(2_000, 200, 1_000);
+    assert!(rebooted.usage_since(&earlier).is_err());
+}
+
+#[test]
+fn test_cpu_total_saturates() {
+    let cpu = StatCpu {
+        user: u64::MAX,
+        nice: 1,
+        system: 0,
+        idle: 0,
+        iowait: 0,
+        irq: 0,
+        softirq: 0,
+        steal: None,
+        guest: None,
+        guest_nice: None,
+    };
+    assert_eq!(cpu.checked_total(), None);
+    assert_eq!(cpu.total(), u64::MAX);
+
+    let cpu = StatCpu {
+        user: 10,
+        nice: 0,
+        system: 0,
+        idle: 70,
+        iowait: 5,
+        irq: 0,
+        softirq: 0,
+        steal: None,
+        guest: None,
+        guest_nice: None,
+    };
+    assert_eq!(cpu.idle_time(), 75);
+    assert_eq!(cpu.busy_time(), 10);
+}
+
+#[test]
+#[cfg(feature = "serde1")]
+fn test_serde_round_trip() {
+    let stat = make_stat(1_535_128_607, 17501, 8212469);
+    let json = serde_json::to_string(&stat).unwrap();
+    let round_tripped: Stat = serde_json::from_str(&json).unwrap();
+    assert_eq!(stat, round_tripped);
 }
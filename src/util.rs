@@ -0,0 +1,73 @@
+//! Low-level helpers for parsing whitespace-separated `/proc` files.
+use crate::Error;
+use std::io::{self, BufRead, BufReader, Read};
+
+/// Splits the next whitespace-delimited token off the front of `input`, returning
+/// `(remainder, token)`, or `None` if `input` is empty after trimming leading whitespace.
+pub(crate) fn parse_token(input: &str) -> Option<(&str, &str)> {
+    let input = input.trim_start();
+    if input.is_empty() {
+        return None;
+    }
+    let end = input.find(char::is_whitespace).unwrap_or(input.len());
+    Some((&input[end..], &input[..end]))
+}
+
+/// Parses the next whitespace-delimited token as a `u64`, returning `(remainder, value)`.
+pub(crate) fn parse_u64(input: &str) -> Option<(&str, u64)> {
+    let (rest, token) = parse_token(input)?;
+    token.parse().ok().map(|value| (rest, value))
+}
+
+/// Strips any leading whitespace from `input`.
+pub(crate) fn consume_space(input: &str) -> &str {
+    input.trim_start()
+}
+
+/// Reads a file one line at a time, handing each line to a caller-supplied parser.
+///
+/// A line is only consumed once some parser successfully accepts it; a failed `parse_line` call
+/// leaves the line available for the next call, so callers can probe "is this one of mine?"
+/// across a handful of candidate parsers (as `Stat::from_read` does to find the end of the
+/// per-cpu lines).
+pub(crate) struct LineParser<R> {
+    lines: io::Lines<BufReader<R>>,
+    pending: Option<String>,
+}
+
+impl<R: Read> LineParser<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        LineParser {
+            lines: BufReader::new(reader).lines(),
+            pending: None,
+        }
+    }
+
+    fn next_line(&mut self) -> io::Result<String> {
+        if let Some(line) = self.pending.take() {
+            return Ok(line);
+        }
+        self.lines.next().unwrap_or_else(|| {
+            Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "unexpected end of file",
+            ))
+        })
+    }
+
+    /// Reads the next line and parses it with `f`, converting a parse failure into an
+    /// `io::Error`. On failure the line is retained for the next call to `parse_line`.
+    pub(crate) fn parse_line<T, E>(&mut self, f: impl Fn(&str) -> Result<T, E>) -> io::Result<T>
+    where
+        E: Into<Error>,
+    {
+        let line = self.next_line()?;
+        match f(&line) {
+            Ok(value) => Ok(value),
+            Err(e) => {
+                self.pending = Some(line);
+                Err(io::Error::new(io::ErrorKind::InvalidData, e.into()))
+            }
+        }
+    }
+}